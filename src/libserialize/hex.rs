@@ -1,18 +1,85 @@
 //! Hex binary-to-text encoding
+//!
+//! This module is `no_std`-compatible: without the `std` feature it relies
+//! only on `core` and `alloc`. The crate root is responsible for declaring
+//! `#![no_std]` and `extern crate alloc` when the `std` feature is disabled.
 
 pub use self::FromHexError::*;
 
+#[cfg(feature = "std")]
 use std::fmt;
+#[cfg(feature = "std")]
 use std::error;
+#[cfg(feature = "std")]
+use std::string::String;
+#[cfg(feature = "std")]
+use std::vec::Vec;
+#[cfg(not(feature = "std"))]
+use core::fmt;
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
 /// A trait for converting a value to hexadecimal encoding
 pub trait ToHex {
     /// Converts the value of `self` to a hex value, returning the owned
     /// string.
-    fn to_hex(&self) -> String;
+    fn to_hex(&self) -> String {
+        let mut s = String::new();
+        self.write_hex(&mut s).expect("writing to a String cannot fail");
+        s
+    }
+
+    /// Writes the hex value of `self` into `w`, without allocating an
+    /// intermediate `String`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(rustc_private)]
+    ///
+    /// extern crate serialize;
+    /// use std::fmt::Write;
+    /// use serialize::hex::ToHex;
+    ///
+    /// fn main () {
+    ///     let mut s = String::new();
+    ///     [52, 32].write_hex(&mut s).unwrap();
+    ///     println!("{}", s);
+    /// }
+    /// ```
+    fn write_hex<W: fmt::Write>(&self, w: &mut W) -> fmt::Result;
+
+    /// Converts the value of `self` to an uppercase hex value, returning the
+    /// owned string.
+    fn to_hex_upper(&self) -> String {
+        let mut s = String::new();
+        self.write_hex_upper(&mut s).expect("writing to a String cannot fail");
+        s
+    }
+
+    /// Writes the uppercase hex value of `self` into `w`, without allocating
+    /// an intermediate `String`.
+    fn write_hex_upper<W: fmt::Write>(&self, w: &mut W) -> fmt::Result;
 }
 
-const CHARS: &[u8] = b"0123456789abcdef";
+/// Builds a 256-entry table mapping each byte to its two-character hex
+/// representation, so encoding a byte is a single lookup instead of two
+/// shift/mask/index operations.
+const fn build_encode_table(chars: &[u8; 16]) -> [[u8; 2]; 256] {
+    let mut table = [[0u8; 2]; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i][0] = chars[(i >> 4) & 0xf];
+        table[i][1] = chars[i & 0xf];
+        i += 1;
+    }
+    table
+}
+
+const ENCODE_TABLE: [[u8; 2]; 256] = build_encode_table(b"0123456789abcdef");
+const ENCODE_TABLE_UPPER: [[u8; 2]; 256] = build_encode_table(b"0123456789ABCDEF");
 
 impl ToHex for [u8] {
     /// Turn a vector of `u8` bytes into a hexadecimal string.
@@ -33,14 +100,31 @@ impl ToHex for [u8] {
     fn to_hex(&self) -> String {
         let mut v = Vec::with_capacity(self.len() * 2);
         for &byte in self {
-            v.push(CHARS[(byte >> 4) as usize]);
-            v.push(CHARS[(byte & 0xf) as usize]);
+            v.extend_from_slice(&ENCODE_TABLE[byte as usize]);
         }
 
         unsafe {
             String::from_utf8_unchecked(v)
         }
     }
+
+    fn write_hex<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        for &byte in self {
+            let pair = ENCODE_TABLE[byte as usize];
+            w.write_char(pair[0] as char)?;
+            w.write_char(pair[1] as char)?;
+        }
+        Ok(())
+    }
+
+    fn write_hex_upper<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        for &byte in self {
+            let pair = ENCODE_TABLE_UPPER[byte as usize];
+            w.write_char(pair[0] as char)?;
+            w.write_char(pair[1] as char)?;
+        }
+        Ok(())
+    }
 }
 
 /// A trait for converting hexadecimal encoded values
@@ -48,6 +132,22 @@ pub trait FromHex {
     /// Converts the value of `self`, interpreted as hexadecimal encoded data,
     /// into an owned vector of bytes, returning the vector.
     fn from_hex(&self) -> Result<Vec<u8>, FromHexError>;
+
+    /// Decodes `self`, interpreted as hexadecimal encoded data, directly into
+    /// `out`, returning the number of bytes written.
+    ///
+    /// This avoids the heap allocation of `from_hex` by reusing a
+    /// caller-provided buffer. Returns `InvalidBufferLength` if `out` is too
+    /// small to hold the decoded bytes.
+    fn from_hex_into(&self, out: &mut [u8]) -> Result<usize, FromHexError>;
+
+    /// Decodes the first `2*N` hex nibbles of `self` into a fixed-size
+    /// `[u8; N]`, stopping as soon as `N` bytes are collected rather than
+    /// scanning the rest of `self`. Returns `InvalidArrayLength` if `self`
+    /// runs out before `N` bytes are decoded. This is ideal for pulling a
+    /// fixed-width value such as a hash or UUID out of a larger buffer or
+    /// stream without paying to validate the trailing bytes.
+    fn from_hex_array<const N: usize>(&self) -> Result<[u8; N], FromHexError>;
 }
 
 /// Errors that can occur when decoding a hex encoded string
@@ -57,6 +157,11 @@ pub enum FromHexError {
     InvalidHexCharacter(char, usize),
     /// The input had an invalid length
     InvalidHexLength,
+    /// The output buffer was too small to hold the decoded bytes
+    InvalidBufferLength,
+    /// The decoded byte count did not match the requested array length
+    /// (expected, found)
+    InvalidArrayLength(usize, usize),
 }
 
 impl fmt::Display for FromHexError {
@@ -65,20 +170,97 @@ impl fmt::Display for FromHexError {
             InvalidHexCharacter(ch, idx) =>
                 write!(f, "Invalid character '{}' at position {}", ch, idx),
             InvalidHexLength => write!(f, "Invalid input length"),
+            InvalidBufferLength => write!(f, "Invalid output buffer length"),
+            InvalidArrayLength(expected, found) =>
+                write!(f, "Invalid array length: expected {}, found {}", expected, found),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for FromHexError {
     fn description(&self) -> &str {
         match *self {
             InvalidHexCharacter(..) => "invalid character",
             InvalidHexLength => "invalid length",
+            InvalidBufferLength => "invalid buffer length",
+            InvalidArrayLength(..) => "invalid array length",
         }
     }
 }
 
 
+/// Sentinel stored in `DECODE_TABLE` for bytes that are not valid hex digits.
+const INVALID_NIBBLE: u8 = 0xff;
+/// Sentinel stored in `DECODE_TABLE` for whitespace bytes that should be
+/// skipped rather than decoded.
+const SKIP_NIBBLE: u8 = 0xfe;
+
+/// Builds a 256-entry reverse lookup table mapping each ASCII byte to its
+/// nibble value, so decoding a byte is a single table lookup rather than a
+/// multi-arm range match.
+const fn build_decode_table() -> [u8; 256] {
+    let mut table = [INVALID_NIBBLE; 256];
+
+    let mut c = 0u8;
+    while c < 10 {
+        table[(b'0' + c) as usize] = c;
+        c += 1;
+    }
+    let mut c = 0u8;
+    while c < 6 {
+        table[(b'a' + c) as usize] = c + 10;
+        table[(b'A' + c) as usize] = c + 10;
+        c += 1;
+    }
+
+    table[b' ' as usize] = SKIP_NIBBLE;
+    table[b'\r' as usize] = SKIP_NIBBLE;
+    table[b'\n' as usize] = SKIP_NIBBLE;
+    table[b'\t' as usize] = SKIP_NIBBLE;
+
+    table
+}
+
+const DECODE_TABLE: [u8; 256] = build_decode_table();
+
+/// Shared decode core used by `from_hex`, `from_hex_into`, and
+/// `from_hex_array`. Decodes hex nibbles from `s`, invoking `sink` with each
+/// decoded byte; `sink` returns `true` to keep decoding or `false` to stop
+/// early (without validating or even looking at the remainder of `s`).
+/// Returns the number of bytes handed to `sink`.
+fn decode_into(s: &str, mut sink: impl FnMut(u8) -> bool) -> Result<usize, FromHexError> {
+    let mut modulus = 0;
+    let mut buf = 0;
+    let mut written = 0;
+
+    for (idx, byte) in s.bytes().enumerate() {
+        let nibble = DECODE_TABLE[byte as usize];
+        if nibble == SKIP_NIBBLE {
+            continue;
+        }
+        if nibble == INVALID_NIBBLE {
+            let ch = s[idx..].chars().next().unwrap();
+            return Err(InvalidHexCharacter(ch, idx))
+        }
+
+        buf = (buf << 4) | nibble;
+        modulus += 1;
+        if modulus == 2 {
+            modulus = 0;
+            written += 1;
+            if !sink(buf) {
+                return Ok(written);
+            }
+        }
+    }
+
+    match modulus {
+        0 => Ok(written),
+        _ => Err(InvalidHexLength),
+    }
+}
+
 impl FromHex for str {
     /// Converts any hexadecimal encoded string (literal, `@`, `&`, or `~`)
     /// to the byte values it encodes.
@@ -108,36 +290,159 @@ impl FromHex for str {
     fn from_hex(&self) -> Result<Vec<u8>, FromHexError> {
         // This may be an overestimate if there is any whitespace
         let mut b = Vec::with_capacity(self.len() / 2);
-        let mut modulus = 0;
-        let mut buf = 0;
-
-        for (idx, byte) in self.bytes().enumerate() {
-            buf <<= 4;
-
-            match byte {
-                b'A'..=b'F' => buf |= byte - b'A' + 10,
-                b'a'..=b'f' => buf |= byte - b'a' + 10,
-                b'0'..=b'9' => buf |= byte - b'0',
-                b' '|b'\r'|b'\n'|b'\t' => {
-                    buf >>= 4;
-                    continue
-                }
-                _ => {
-                    let ch = self[idx..].chars().next().unwrap();
-                    return Err(InvalidHexCharacter(ch, idx))
-                }
-            }
+        decode_into(self, |byte| { b.push(byte); true })?;
+        Ok(b)
+    }
 
-            modulus += 1;
-            if modulus == 2 {
-                modulus = 0;
-                b.push(buf);
+    /// Decodes `self` directly into `out`, without allocating an
+    /// intermediate `Vec`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(rustc_private)]
+    ///
+    /// extern crate serialize;
+    /// use serialize::hex::FromHex;
+    ///
+    /// fn main () {
+    ///     let mut buf = [0u8; 6];
+    ///     let n = "666f6f626172".from_hex_into(&mut buf).unwrap();
+    ///     assert_eq!(&buf[..n], b"foobar");
+    /// }
+    /// ```
+    fn from_hex_into(&self, out: &mut [u8]) -> Result<usize, FromHexError> {
+        let mut i = 0;
+        let mut overflowed = false;
+
+        decode_into(self, |byte| {
+            if i < out.len() {
+                out[i] = byte;
+                i += 1;
+                true
+            } else {
+                overflowed = true;
+                false
             }
+        })?;
+
+        if overflowed {
+            Err(InvalidBufferLength)
+        } else {
+            Ok(i)
         }
+    }
 
-        match modulus {
-            0 => Ok(b),
-            _ => Err(InvalidHexLength),
+    /// Decodes the leading `2*N` hex nibbles of `self` into a
+    /// stack-allocated `[u8; N]`, without a heap allocation, stopping before
+    /// the rest of `self` is scanned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![feature(rustc_private)]
+    ///
+    /// extern crate serialize;
+    /// use serialize::hex::FromHex;
+    ///
+    /// fn main () {
+    ///     let arr: [u8; 3] = "666f6ftrailinggarbage".from_hex_array().unwrap();
+    ///     assert_eq!(&arr, b"foo");
+    /// }
+    /// ```
+    fn from_hex_array<const N: usize>(&self) -> Result<[u8; N], FromHexError> {
+        let mut out = [0u8; N];
+        if N == 0 {
+            return Ok(out);
+        }
+
+        let mut i = 0;
+        let written = decode_into(self, |byte| {
+            out[i] = byte;
+            i += 1;
+            i < N
+        })?;
+
+        if written == N {
+            Ok(out)
+        } else {
+            Err(InvalidArrayLength(N, written))
+        }
+    }
+}
+
+/// `serde` (de)serialization of byte buffers as hex strings.
+///
+/// Intended for use with `#[serde(with = "hex::serde")]` on a `Vec<u8>`
+/// field; see [`serde::array`] for `[u8; N]` fields, whose decoded length is
+/// fixed at compile time and so cannot share this module's `Vec<u8>`-typed
+/// `deserialize`. Human-readable formats (JSON, TOML) see a lowercase hex
+/// string; binary formats see the raw bytes.
+#[cfg(feature = "serde")]
+pub mod serde {
+    use super::{FromHex, String, ToHex, Vec};
+    use serde::{Deserialize, Deserializer, Serializer, de::Error as _};
+
+    /// Serializes `bytes` as a lowercase hex string for human-readable
+    /// formats, or as raw bytes otherwise.
+    ///
+    /// Works for any byte-slice-like field, including `Vec<u8>` and
+    /// `[u8; N]`, so it is reused by [`array::deserialize`]'s companion
+    /// serializer.
+    pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where T: AsRef<[u8]>, S: Serializer
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&bytes.as_ref().to_hex())
+        } else {
+            serializer.serialize_bytes(bytes.as_ref())
+        }
+    }
+
+    /// Deserializes a `Vec<u8>` from a lowercase or uppercase hex string for
+    /// human-readable formats, or from raw bytes otherwise, mirroring
+    /// [`serialize`]'s format branch.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+        where D: Deserializer<'de>
+    {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            s.from_hex().map_err(D::Error::custom)
+        } else {
+            Vec::<u8>::deserialize(deserializer)
+        }
+    }
+
+    /// `serde` (de)serialization of fixed-size `[u8; N]` fields as hex
+    /// strings, for use with `#[serde(with = "hex::serde::array")]`.
+    pub mod array {
+        pub use super::serialize;
+
+        use super::{FromHex, String, Vec};
+        use crate::hex::FromHexError::InvalidArrayLength;
+        use core::convert::TryInto;
+        use serde::{Deserialize, Deserializer, de::Error as _};
+
+        /// Deserializes a `[u8; N]` from a lowercase or uppercase hex string
+        /// for human-readable formats, or from raw bytes otherwise,
+        /// mirroring [`serialize`]'s format branch. Errors if the decoded
+        /// length differs from `N`.
+        pub fn deserialize<'de, D, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error>
+            where D: Deserializer<'de>
+        {
+            if deserializer.is_human_readable() {
+                let s = String::deserialize(deserializer)?;
+                let v = s.from_hex().map_err(D::Error::custom)?;
+                let len = v.len();
+                v.try_into().map_err(|_| D::Error::custom(InvalidArrayLength(N, len)))
+            } else {
+                // `serde` only implements `Deserialize` for arrays of a
+                // literal fixed size, not one generic over `N`, so read the
+                // raw bytes into a `Vec<u8>` first and convert.
+                let v = Vec::<u8>::deserialize(deserializer)?;
+                let len = v.len();
+                v.try_into().map_err(|_| D::Error::custom(InvalidArrayLength(N, len)))
+            }
         }
     }
 }
@@ -147,12 +452,59 @@ mod tests {
     extern crate test;
     use test::Bencher;
     use crate::hex::{FromHex, ToHex};
+    use crate::hex::FromHexError::*;
 
     #[test]
     pub fn test_to_hex() {
         assert_eq!("foobar".as_bytes().to_hex(), "666f6f626172");
     }
 
+    #[test]
+    pub fn test_write_hex() {
+        let mut s = String::new();
+        "foobar".as_bytes().write_hex(&mut s).unwrap();
+        assert_eq!(s, "666f6f626172");
+    }
+
+    #[test]
+    pub fn test_to_hex_upper() {
+        assert_eq!("foobar".as_bytes().to_hex_upper(), "666F6F626172");
+    }
+
+    #[test]
+    pub fn test_from_hex_into_okay() {
+        let mut buf = [0u8; 6];
+        let n = "666f6f626172".from_hex_into(&mut buf).unwrap();
+        assert_eq!(n, 6);
+        assert_eq!(&buf[..n], b"foobar");
+    }
+
+    #[test]
+    pub fn test_from_hex_into_buffer_too_small() {
+        let mut buf = [0u8; 3];
+        assert!(matches!("666f6f626172".from_hex_into(&mut buf), Err(InvalidBufferLength)));
+    }
+
+    #[test]
+    pub fn test_from_hex_array_okay() {
+        let arr: [u8; 6] = "666f6f626172".from_hex_array().unwrap();
+        assert_eq!(&arr, b"foobar");
+    }
+
+    #[test]
+    pub fn test_from_hex_array_too_short() {
+        let result: Result<[u8; 3], _> = "666f".from_hex_array();
+        assert!(matches!(result, Err(InvalidArrayLength(3, 2))));
+    }
+
+    #[test]
+    pub fn test_from_hex_array_stops_after_n_bytes() {
+        // Trailing data past the requested N bytes is never scanned, so
+        // invalid hex characters there don't cause an error.
+        let arr: [u8; 3] = "666f6fnotvalidhex!!".from_hex_array().unwrap();
+        assert_eq!(&arr, b"foo");
+    }
+
     #[test]
     pub fn test_from_hex_okay() {
         assert_eq!("666f6f626172".from_hex().unwrap(),
@@ -218,4 +570,74 @@ mod tests {
         });
         b.bytes = sb.len() as u64;
     }
+
+    #[bench]
+    pub fn bench_to_hex_large(b: &mut Bencher) {
+        let bytes = vec![0x5au8; 16 * 1024];
+        b.iter(|| {
+            bytes.to_hex();
+        });
+        b.bytes = bytes.len() as u64;
+    }
+
+    #[bench]
+    pub fn bench_from_hex_large(b: &mut Bencher) {
+        let bytes = vec![0x5au8; 16 * 1024];
+        let s = bytes.to_hex();
+        b.iter(|| {
+            s.from_hex().unwrap();
+        });
+        b.bytes = s.len() as u64;
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper {
+        #[serde(with = "crate::hex::serde")]
+        bytes: Vec<u8>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ArrayWrapper {
+        #[serde(with = "crate::hex::serde::array")]
+        bytes: [u8; 3],
+    }
+
+    #[test]
+    pub fn test_vec_json_round_trip() {
+        let w = Wrapper { bytes: b"foo".to_vec() };
+        let json = serde_json::to_string(&w).unwrap();
+        assert_eq!(json, r#"{"bytes":"666f6f"}"#);
+        let back: Wrapper = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.bytes, b"foo");
+    }
+
+    #[test]
+    pub fn test_vec_bincode_round_trip() {
+        let w = Wrapper { bytes: b"foo".to_vec() };
+        let encoded = bincode::serialize(&w).unwrap();
+        let back: Wrapper = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(back.bytes, b"foo");
+    }
+
+    #[test]
+    pub fn test_array_json_round_trip() {
+        let w = ArrayWrapper { bytes: *b"foo" };
+        let json = serde_json::to_string(&w).unwrap();
+        assert_eq!(json, r#"{"bytes":"666f6f"}"#);
+        let back: ArrayWrapper = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.bytes, *b"foo");
+    }
+
+    #[test]
+    pub fn test_array_bincode_round_trip() {
+        let w = ArrayWrapper { bytes: *b"foo" };
+        let encoded = bincode::serialize(&w).unwrap();
+        let back: ArrayWrapper = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(back.bytes, *b"foo");
+    }
 }
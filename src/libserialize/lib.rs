@@ -0,0 +1,9 @@
+//! Support code for encoding and decoding types to and from various formats.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(test, feature(test))]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+pub mod hex;